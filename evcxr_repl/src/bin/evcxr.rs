@@ -16,28 +16,94 @@ use evcxr;
 
 use colored::*;
 use evcxr::{CommandContext, CompilationError, Error};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
 use rustyline::completion::Completer;
-use rustyline::highlight::Highlighter;
+use rustyline::highlight::{Highlighter, MatchingBracketHighlighter};
 use rustyline::hint::{Hinter, HistoryHinter};
 use rustyline::line_buffer::LineBuffer;
-use rustyline::validate::{ValidationResult, Validator};
-use rustyline::{error::ReadlineError, Context, Editor, Helper};
+use rustyline::validate::{MatchingBracketValidator, ValidationResult, Validator};
+use rustyline::{error::ReadlineError, CompletionType, Context, Editor, Helper};
+use serde::Serialize;
 use std::borrow::Cow;
+use std::cell::RefCell;
 use std::fs;
 use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use std::sync::mpsc;
+use std::time::Duration;
 use structopt::StructOpt;
 use syntect::dumps;
 use syntect::easy::HighlightLines;
 use syntect::highlighting::ThemeSet;
 use syntect::parsing::SyntaxSet;
-use syntect::util::as_24_bit_terminal_escaped;
 
 const PROMPT: &str = ">> ";
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(format!(
+                "Unknown output format '{}', expected 'text' or 'json'",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum JsonRecord {
+    Output {
+        text: String,
+    },
+    Error {
+        diagnostics: Vec<JsonDiagnostic>,
+    },
+    Timing {
+        total_ms: u128,
+        phases: Vec<JsonPhase>,
+    },
+}
+
+#[derive(Serialize)]
+struct JsonPhase {
+    name: String,
+    ms: u128,
+}
+
+#[derive(Serialize)]
+struct JsonDiagnostic {
+    message: String,
+    level: String,
+    is_from_user_code: bool,
+    help: Vec<String>,
+    evcxr_extra_hint: Option<String>,
+    spans: Vec<JsonDiagnosticSpan>,
+}
+
+#[derive(Serialize)]
+struct JsonDiagnosticSpan {
+    start_line: usize,
+    start_column: usize,
+    end_column: usize,
+    label: String,
+}
+
 struct Repl {
-    command_context: CommandContext,
+    command_context: Rc<RefCell<CommandContext>>,
     ide_mode: bool,
+    output_format: OutputFormat,
 }
 
 fn send_output<T: io::Write + Send + 'static>(channel: mpsc::Receiver<String>, mut output: T) {
@@ -51,32 +117,61 @@ fn send_output<T: io::Write + Send + 'static>(channel: mpsc::Receiver<String>, m
 }
 
 impl Repl {
-    fn new(ide_mode: bool) -> Result<Repl, Error> {
+    fn new(ide_mode: bool, output_format: OutputFormat) -> Result<Repl, Error> {
         let (command_context, outputs) = CommandContext::new()?;
         send_output(outputs.stdout, io::stdout());
         send_output(outputs.stderr, io::stderr());
         let mut repl = Repl {
-            command_context,
+            command_context: Rc::new(RefCell::new(command_context)),
             ide_mode,
+            output_format,
         };
         repl.execute(":load_config");
         Ok(repl)
     }
 
+    fn print_json(&self, record: &JsonRecord) {
+        println!("{}", serde_json::to_string(record).unwrap());
+    }
+
     fn execute(&mut self, to_run: &str) {
-        let success = match self.command_context.execute(to_run) {
+        let success = match self.command_context.borrow_mut().execute(to_run) {
             Ok(output) => {
                 if let Some(text) = output.get("text/plain") {
-                    println!("{}", text);
+                    match self.output_format {
+                        OutputFormat::Text => println!("{}", text),
+                        OutputFormat::Json => self.print_json(&JsonRecord::Output {
+                            text: text.to_string(),
+                        }),
+                    }
                 }
                 if let Some(duration) = output.timing {
-                    println!("{}", format!("Took {}ms", duration.as_millis()).blue());
+                    match self.output_format {
+                        OutputFormat::Text => {
+                            println!("{}", format!("Took {}ms", duration.as_millis()).blue());
 
-                    for phase in output.phases {
-                        println!(
-                            "{}",
-                            format!("  {}: {}ms", phase.name, phase.duration.as_millis()).blue()
-                        );
+                            for phase in &output.phases {
+                                println!(
+                                    "{}",
+                                    format!("  {}: {}ms", phase.name, phase.duration.as_millis())
+                                        .blue()
+                                );
+                            }
+                        }
+                        OutputFormat::Json => {
+                            let phases = output
+                                .phases
+                                .iter()
+                                .map(|phase| JsonPhase {
+                                    name: phase.name.clone(),
+                                    ms: phase.duration.as_millis(),
+                                })
+                                .collect();
+                            self.print_json(&JsonRecord::Timing {
+                                total_ms: duration.as_millis(),
+                                phases,
+                            });
+                        }
                     }
                 }
                 true
@@ -86,7 +181,19 @@ impl Repl {
                 false
             }
             Err(err) => {
-                eprintln!("{}", format!("{}", err).bright_red());
+                match self.output_format {
+                    OutputFormat::Text => eprintln!("{}", format!("{}", err).bright_red()),
+                    OutputFormat::Json => self.print_json(&JsonRecord::Error {
+                        diagnostics: vec![JsonDiagnostic {
+                            message: format!("{}", err),
+                            level: "error".to_string(),
+                            is_from_user_code: false,
+                            help: Vec::new(),
+                            evcxr_extra_hint: None,
+                            spans: Vec::new(),
+                        }],
+                    }),
+                }
                 false
             }
         };
@@ -98,6 +205,11 @@ impl Repl {
     }
 
     fn display_errors(&mut self, errors: Vec<CompilationError>) {
+        if self.output_format == OutputFormat::Json {
+            let diagnostics = errors.iter().map(json_diagnostic).collect();
+            self.print_json(&JsonRecord::Error { diagnostics });
+            return;
+        }
         for error in errors {
             if error.is_from_user_code() {
                 for spanned_message in error.spanned_messages() {
@@ -135,6 +247,36 @@ impl Repl {
     }
 }
 
+fn json_diagnostic(error: &CompilationError) -> JsonDiagnostic {
+    let spans = error
+        .spanned_messages()
+        .iter()
+        .filter_map(|spanned_message| {
+            spanned_message
+                .span
+                .as_ref()
+                .map(|span| JsonDiagnosticSpan {
+                    start_line: span.start_line,
+                    start_column: span.start_column,
+                    end_column: span.end_column,
+                    label: spanned_message.label.clone(),
+                })
+        })
+        .collect();
+    JsonDiagnostic {
+        message: error.message().to_string(),
+        level: "error".to_string(),
+        is_from_user_code: error.is_from_user_code(),
+        help: error
+            .help()
+            .into_iter()
+            .map(|help| help.to_string())
+            .collect(),
+        evcxr_extra_hint: error.evcxr_extra_hint().map(|hint| hint.to_string()),
+        spans,
+    }
+}
+
 fn readline_direct(prompt: &str) -> rustyline::Result<String> {
     use std::io::Write;
 
@@ -152,43 +294,250 @@ fn readline_direct(prompt: &str) -> rustyline::Result<String> {
     }
 }
 
+const DEFAULT_THEME: &str = "Solarized (dark)";
+
+/// How many colors the terminal we're writing to supports, cheaply detected
+/// from the environment rather than assumed, so highlighting degrades
+/// gracefully instead of emitting escapes the terminal can't render.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorDepth {
+    TrueColor,
+    Ansi256,
+    Ansi16,
+}
+
+impl ColorDepth {
+    fn detect() -> ColorDepth {
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorDepth::TrueColor;
+        }
+        if std::env::var("TERM")
+            .map(|term| term.contains("256color"))
+            .unwrap_or(false)
+        {
+            return ColorDepth::Ansi256;
+        }
+        ColorDepth::Ansi16
+    }
+}
+
+/// Copies `text` (the slice of the original line covered by one syntect
+/// style run, starting at `chunk_start`) into `out`, wrapping any byte
+/// offset that appears in `emphasize` (e.g. a matching bracket pair) in
+/// bold+underline. Codes 22/24 turn those back off without touching the
+/// surrounding foreground color escape, so emphasis blends into whichever
+/// color the syntax highlighting already chose for that character.
+fn push_text_with_emphasis(out: &mut String, text: &str, chunk_start: usize, emphasize: &[usize]) {
+    for (i, ch) in text.char_indices() {
+        let emphasized = emphasize.contains(&(chunk_start + i));
+        if emphasized {
+            out.push_str("\x1b[1;4m");
+        }
+        out.push(ch);
+        if emphasized {
+            out.push_str("\x1b[22;24m");
+        }
+    }
+}
+
+/// Renders a syntect highlight as 24-bit (true color) escapes, emphasizing
+/// the byte offsets in `emphasize` (e.g. a matching bracket pair).
+fn as_24_bit_color_terminal_escaped(
+    ranges: &[(syntect::highlighting::Style, &str)],
+    emphasize: &[usize],
+) -> String {
+    let mut escaped = String::new();
+    let mut offset = 0;
+    for (style, text) in ranges {
+        let foreground = style.foreground;
+        escaped.push_str(&format!(
+            "\x1b[38;2;{};{};{}m",
+            foreground.r, foreground.g, foreground.b
+        ));
+        push_text_with_emphasis(&mut escaped, text, offset, emphasize);
+        offset += text.len();
+    }
+    escaped.push_str("\x1b[0m");
+    escaped
+}
+
+/// Renders a syntect highlight as 256-color (8-bit) escapes, for terminals
+/// that advertise `256color` support but not `COLORTERM=truecolor`.
+fn as_256_color_terminal_escaped(
+    ranges: &[(syntect::highlighting::Style, &str)],
+    emphasize: &[usize],
+) -> String {
+    let mut escaped = String::new();
+    let mut offset = 0;
+    for (style, text) in ranges {
+        let foreground = style.foreground;
+        let r = u16::from(foreground.r) * 5 / 255;
+        let g = u16::from(foreground.g) * 5 / 255;
+        let b = u16::from(foreground.b) * 5 / 255;
+        let color = 16 + 36 * r + 6 * g + b;
+        escaped.push_str(&format!("\x1b[38;5;{}m", color));
+        push_text_with_emphasis(&mut escaped, text, offset, emphasize);
+        offset += text.len();
+    }
+    escaped.push_str("\x1b[0m");
+    escaped
+}
+
+/// Renders a syntect highlight using the 16 basic ANSI colors, for terminals
+/// that don't advertise any richer color support.
+fn as_16_color_terminal_escaped(
+    ranges: &[(syntect::highlighting::Style, &str)],
+    emphasize: &[usize],
+) -> String {
+    let mut escaped = String::new();
+    let mut offset = 0;
+    for (style, text) in ranges {
+        let foreground = style.foreground;
+        let bright =
+            u16::from(foreground.r) + u16::from(foreground.g) + u16::from(foreground.b) > 3 * 127;
+        let base = (foreground.r > 127) as u8
+            | ((foreground.g > 127) as u8) << 1
+            | ((foreground.b > 127) as u8) << 2;
+        let code = if bright { 90 + base } else { 30 + base };
+        escaped.push_str(&format!("\x1b[{}m", code));
+        push_text_with_emphasis(&mut escaped, text, offset, emphasize);
+        offset += text.len();
+    }
+    escaped.push_str("\x1b[0m");
+    escaped
+}
+
+/// Finds the bracket pair that the helper should emphasize this redraw: the
+/// bracket immediately before or at the cursor, and its match. Returns the
+/// byte offsets of both characters, or `None` if the cursor isn't next to a
+/// bracket, or the bracket it's next to has no match yet.
+fn find_matching_bracket(line: &str, pos: usize) -> Option<(usize, usize)> {
+    const PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+    let chars: Vec<(usize, char)> = line.char_indices().collect();
+    let mut candidates = Vec::new();
+    if pos > 0 {
+        candidates.push(pos - 1);
+    }
+    candidates.push(pos);
+
+    for candidate in candidates {
+        let at_candidate = chars.iter().find(|&&(i, _)| i == candidate);
+        let (idx, ch) = match at_candidate {
+            Some(&(idx, ch)) => (idx, ch),
+            None => continue,
+        };
+        if let Some(&(open, close)) = PAIRS.iter().find(|&(open, _)| *open == ch) {
+            let mut depth = 0;
+            for &(i, c) in chars.iter().filter(|&&(i, _)| i >= idx) {
+                if c == open {
+                    depth += 1;
+                } else if c == close {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((idx, i));
+                    }
+                }
+            }
+        } else if let Some(&(open, close)) = PAIRS.iter().find(|&(_, close)| *close == ch) {
+            let mut depth = 0;
+            for &(i, c) in chars.iter().rev().filter(|&&(i, _)| i <= idx) {
+                if c == close {
+                    depth += 1;
+                } else if c == open {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some((i, idx));
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
 struct RustHighlighter {
     ps: SyntaxSet,
     ts: ThemeSet,
+    theme_name: String,
+    color_depth: ColorDepth,
 }
 
 impl RustHighlighter {
-    fn new() -> RustHighlighter {
+    fn new(theme_name: String, color_depth: ColorDepth) -> RustHighlighter {
         let ps: SyntaxSet = dumps::from_binary(include_bytes!("../../assets/syntaxes.bin"));
         let ts: ThemeSet = dumps::from_binary(include_bytes!("../../assets/themes.bin"));
-        RustHighlighter { ps, ts }
+        let theme_name = if ts.themes.contains_key(&theme_name) {
+            theme_name
+        } else {
+            eprintln!(
+                "{}",
+                format!(
+                    "Unknown theme '{}', falling back to '{}'",
+                    theme_name, DEFAULT_THEME
+                )
+                .bright_red()
+            );
+            DEFAULT_THEME.to_string()
+        };
+        RustHighlighter {
+            ps,
+            ts,
+            theme_name,
+            color_depth,
+        }
     }
 
-    fn highlight(&self, line: &str, _pos: usize) -> String {
+    fn highlight(&self, line: &str, emphasize: &[usize]) -> String {
         let syntax = self.ps.find_syntax_by_name("Rust").unwrap();
-        let theme = &self.ts.themes["Solarized (dark)"];
+        let theme = &self.ts.themes[&self.theme_name];
         let mut h = HighlightLines::new(syntax, theme);
-        as_24_bit_terminal_escaped(&h.highlight(line, &self.ps), false)
+        let ranges = h.highlight(line, &self.ps);
+        match self.color_depth {
+            ColorDepth::TrueColor => as_24_bit_color_terminal_escaped(&ranges, emphasize),
+            ColorDepth::Ansi256 => as_256_color_terminal_escaped(&ranges, emphasize),
+            ColorDepth::Ansi16 => as_16_color_terminal_escaped(&ranges, emphasize),
+        }
     }
 }
 
 struct RLHelper {
     highlighter: RustHighlighter,
+    bracket_highlighter: MatchingBracketHighlighter,
+    bracket_validator: MatchingBracketValidator,
     hinter: HistoryHinter,
+    command_context: Rc<RefCell<CommandContext>>,
 }
 
 impl RLHelper {
-    fn new() -> RLHelper {
+    fn new(
+        command_context: Rc<RefCell<CommandContext>>,
+        theme_name: String,
+        color_depth: ColorDepth,
+    ) -> RLHelper {
         RLHelper {
-            highlighter: RustHighlighter::new(),
+            highlighter: RustHighlighter::new(theme_name, color_depth),
+            bracket_highlighter: MatchingBracketHighlighter::new(),
+            bracket_validator: MatchingBracketValidator::new(),
             hinter: HistoryHinter {},
+            command_context,
         }
     }
 }
 
 impl Highlighter for RLHelper {
     fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
-        Cow::Owned(self.highlighter.highlight(line, pos))
+        // `MatchingBracketHighlighter` tells us whether the cursor is next to
+        // a bracket; we locate its match ourselves so the emphasis can be
+        // layered into our syntect-colored output instead of replacing it.
+        let emphasize = if self.bracket_highlighter.highlight_char(line, pos) {
+            find_matching_bracket(line, pos)
+                .map(|(open, close)| vec![open, close])
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+        Cow::Owned(self.highlighter.highlight(line, &emphasize))
     }
 
     fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
@@ -208,21 +557,214 @@ impl Hinter for RLHelper {
 
 impl Completer for RLHelper {
     type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        match self.command_context.borrow_mut().completions(line, pos) {
+            Ok(completions) => Ok((
+                completions.start_offset,
+                completions
+                    .completions
+                    .into_iter()
+                    .map(|c| c.code)
+                    .collect(),
+            )),
+            Err(_) => Ok((pos, Vec::new())),
+        }
+    }
 }
 
 impl Helper for RLHelper {}
 
 impl Validator for RLHelper {
     fn validate(&self, line: &mut LineBuffer) -> ValidationResult {
+        if line.starts_with(':') {
+            return ValidationResult::Valid(None);
+        }
+
+        // Unclosed brackets always mean there's more to type, regardless of
+        // whether `syn` can already make sense of what's there so far; a
+        // stray or mismatched closing bracket is reported as invalid.
+        match self.bracket_validator.validate(line) {
+            ValidationResult::Valid(_) => {}
+            incomplete_or_invalid => return incomplete_or_invalid,
+        }
+
+        // Parse as a function body so that multiple statements, item
+        // definitions and a trailing expression (e.g. defining a function
+        // then calling it) all validate, not just a single `syn::Stmt`.
         let code = format!("fn evcxr() {{ {} }}", line.as_str());
-        if line.starts_with(':') || syn::parse_str::<syn::Stmt>(&code).is_ok() {
-            ValidationResult::Valid(None)
-        } else {
-            ValidationResult::Incomplete
+        match syn::parse_str::<syn::ItemFn>(&code) {
+            Ok(_) => ValidationResult::Valid(None),
+            Err(err) => {
+                if syn::parse_file(line.as_str()).is_ok() {
+                    ValidationResult::Valid(None)
+                } else if err.to_string().contains("unexpected end of input") {
+                    // Because the whole line is parsed as one function body,
+                    // anything still mid-composition (`let x = 5` with no
+                    // `;` yet, `if cond` with no block yet, `let y =` with no
+                    // initializer yet, ...) makes `syn` run out of tokens
+                    // looking for what comes next, rather than finding a
+                    // token it can reject outright. Treat that as "keep
+                    // typing", same as an unbalanced bracket.
+                    ValidationResult::Incomplete
+                } else {
+                    // Some other, structural parse failure: the line can't
+                    // be fixed by typing more on the next one, so say why.
+                    ValidationResult::Invalid(Some(format!("\n{}", err)))
+                }
+            }
+        }
+    }
+}
+
+/// Splits `source` into its top-level items/statements, one chunk per
+/// blank-line-separated block, so that each can be fed to `Repl::execute`
+/// one at a time, same as lines typed interactively. A block is only split
+/// on a blank line that sits outside every bracket, so an item with a blank
+/// line in its body (or a bare multi-line statement) stays whole. Unlike
+/// re-rendering through `syn`/`quote`, this keeps each chunk's original line
+/// numbers (padded with leading blank lines) so that compile-error
+/// line/column numbers still point at the right place in the watched file.
+fn split_items(source: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut chunk_start_line = 0;
+    let mut depth: i32 = 0;
+
+    for (line_no, line) in source.lines().enumerate() {
+        for ch in line.chars() {
+            match ch {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ => {}
+            }
+        }
+        if line.trim().is_empty() && depth <= 0 {
+            if !current.is_empty() {
+                chunks.push(render_chunk(chunk_start_line, &current));
+                current.clear();
+            }
+            continue;
+        }
+        if current.is_empty() {
+            chunk_start_line = line_no;
+        }
+        current.push(line);
+    }
+    if !current.is_empty() {
+        chunks.push(render_chunk(chunk_start_line, &current));
+    }
+    chunks
+}
+
+fn render_chunk(start_line: usize, lines: &[&str]) -> String {
+    let mut chunk = "\n".repeat(start_line);
+    chunk.push_str(&lines.join("\n"));
+    chunk
+}
+
+fn run_watched_file(repl: &mut Repl, path: &Path) {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!(
+                "{}",
+                format!("Failed to read {}: {}", path.display(), error).bright_red()
+            );
+            return;
+        }
+    };
+    // Clear the screen and scroll back to the top before re-printing, so
+    // each cycle's output isn't just appended below the last one.
+    print!("\x1b[2J\x1b[H");
+    println!(
+        "{}",
+        format!("--- re-running {} ---", path.display()).blue()
+    );
+    for item in split_items(&source) {
+        repl.execute(&item);
+    }
+}
+
+/// Watches `path` for changes, re-running its contents through `repl` every
+/// time it's saved. Bursts of filesystem events (e.g. an editor that writes
+/// the file in several steps) are coalesced by `notify`'s built-in debounce
+/// before we react.
+///
+/// We watch `path`'s parent directory rather than the file itself: editors
+/// that save via an atomic rename (vim, and most "safe write" modes) replace
+/// the inode at `path`, which stops a direct `NonRecursive` watch on the file
+/// from seeing any further events.
+fn watch_file(mut repl: Repl, path: PathBuf) {
+    let watch_dir = path
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    let (tx, rx) = mpsc::channel();
+    let mut watcher: RecommendedWatcher =
+        Watcher::new(tx, Duration::from_millis(200)).expect("failed to start file watcher");
+    watcher
+        .watch(watch_dir, RecursiveMode::NonRecursive)
+        .unwrap_or_else(|error| panic!("failed to watch {}: {}", watch_dir.display(), error));
+
+    println!(
+        "Watching {} for changes. Press Ctrl-C to stop.",
+        path.display()
+    );
+    run_watched_file(&mut repl, &path);
+    while let Ok(event) = rx.recv() {
+        // Drain any further events from the same save so that a burst of
+        // writes only triggers one re-run, still re-running if any of them
+        // touched the watched file.
+        let mut touched = event_touches(&event, &path);
+        while let Ok(event) = rx.try_recv() {
+            touched |= event_touches(&event, &path);
+        }
+        if touched {
+            run_watched_file(&mut repl, &path);
         }
     }
 }
 
+fn event_touches(event: &notify::DebouncedEvent, path: &Path) -> bool {
+    match event {
+        notify::DebouncedEvent::Create(event_path)
+        | notify::DebouncedEvent::Write(event_path)
+        | notify::DebouncedEvent::Chmod(event_path)
+        | notify::DebouncedEvent::Rename(_, event_path) => event_path == path,
+        // Notices are sent immediately, ahead of the debounce; the debounced
+        // Write/Remove/Rename for the same path follows once things settle.
+        notify::DebouncedEvent::NoticeWrite(_) | notify::DebouncedEvent::NoticeRemove(_) => false,
+        _ => true,
+    }
+}
+
+/// Reads a `theme = "..."` key out of `config.toml` in the config
+/// directory, if present. This is deliberately a separate file from
+/// `init.evcxr`: that one is executed line-by-line as REPL input by
+/// `:load_config`, where a bare `theme = "..."` line isn't a `:`-command or
+/// valid Rust and would fail to compile on every startup.
+fn load_theme_name(config_dir: &Path) -> Option<String> {
+    let contents = fs::read_to_string(config_dir.join("config.toml")).ok()?;
+    for line in contents.lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("theme") {
+            if let Some(value) = value.trim_start().strip_prefix('=') {
+                let value = value.trim().trim_matches('"');
+                if !value.is_empty() {
+                    return Some(value.to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
 #[derive(StructOpt, Debug)]
 #[structopt(name = "evcxr")]
 struct Options {
@@ -233,6 +775,14 @@ struct Options {
     /// Optimization level (0, 1 or 2)
     #[structopt(long, default_value = "")]
     opt: String,
+    /// Watch a source file and re-evaluate it each time it's saved, instead
+    /// of reading from an interactive prompt.
+    #[structopt(long)]
+    watch: Option<PathBuf>,
+    /// Output format for evaluation results: "text" (default, colored for a
+    /// terminal) or "json" (one JSON record per line, for tooling).
+    #[structopt(long, default_value = "text")]
+    output_format: OutputFormat,
 }
 
 fn main() {
@@ -241,7 +791,7 @@ fn main() {
     let options = Options::from_args();
 
     println!("Welcome to evcxr. For help, type :help");
-    let mut repl = match Repl::new(options.ide_mode) {
+    let mut repl = match Repl::new(options.ide_mode, options.output_format) {
         Ok(c) => c,
         Err(error) => {
             eprintln!("{}", error);
@@ -249,12 +799,32 @@ fn main() {
         }
     };
 
-    repl.command_context.set_opt_level(&options.opt).ok();
+    repl.command_context
+        .borrow_mut()
+        .set_opt_level(&options.opt)
+        .ok();
+
+    if let Some(watch_path) = options.watch.clone() {
+        watch_file(repl, watch_path);
+        return;
+    }
 
-    let mut editor = Editor::new();
-    editor.set_helper(Some(RLHelper::new()));
-    let mut opt_history_file = None;
     let config_dir = evcxr::config_dir();
+    let theme_name = config_dir
+        .as_ref()
+        .and_then(|config_dir| load_theme_name(config_dir))
+        .unwrap_or_else(|| DEFAULT_THEME.to_string());
+
+    let rl_config = rustyline::Config::builder()
+        .completion_type(CompletionType::List)
+        .build();
+    let mut editor = Editor::with_config(rl_config);
+    editor.set_helper(Some(RLHelper::new(
+        Rc::clone(&repl.command_context),
+        theme_name,
+        ColorDepth::detect(),
+    )));
+    let mut opt_history_file = None;
     if let Some(config_dir) = &config_dir {
         fs::create_dir_all(config_dir).ok();
         let history_file = config_dir.join("history.txt");